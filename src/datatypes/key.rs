@@ -1,7 +1,6 @@
 use std::borrow::Cow;
 
-use datatypes::InputMode;
-use datatypes::InputMode::*;
+use datatypes::TermMode;
 
 use self::Key::*;
 
@@ -35,11 +34,11 @@ pub enum Key {
 
 impl Key {
 
-    pub fn as_code(&self, mode: InputMode, modifiers: Modifiers) -> Option<String> {
-        match mode {
-            Ansi        => self.compatible_code(modifiers, true),
-            Application => self.compatible_code(modifiers, false),
-            Extended    => self.extended_code(modifiers),
+    pub fn as_code(&self, mode: TermMode, modifiers: Modifiers) -> Option<String> {
+        if mode.contains(TermMode::EXTENDED_KEYBOARD) {
+            self.extended_code(modifiers)
+        } else {
+            self.compatible_code(modifiers, !mode.contains(TermMode::APPLICATION_CURSOR))
         }
     }
 
@@ -59,31 +58,59 @@ impl Key {
                 | AltRight(_)
                 | CapsLock(_)                   => unreachable!(),
             MetaLeft(true) | MetaRight(true)    => None,
-            PageUp(true)                        => tilde_key(modifiers, '5'),
-            PageDown(true)                      => tilde_key(modifiers, '6'),
+            PageUp(true)                        => tilde_key(modifiers, "5"),
+            PageDown(true)                      => tilde_key(modifiers, "6"),
             Home(true)                          => term_key(modifiers, 'H', true),
             End(true)                           => term_key(modifiers, 'F', true),
-            Insert(true)                        => tilde_key(modifiers, '2'),
-            Delete(true)                        => tilde_key(modifiers, '3'),
-            NumLock(_)                          => unimplemented!(),
-            ScrollLock(_)                       => unimplemented!(),
-            Function(..)                        => unimplemented!(),
+            Insert(true)                        => tilde_key(modifiers, "2"),
+            Delete(true)                        => tilde_key(modifiers, "3"),
+            Function(true, n)                   => function_key(modifiers, n),
             _                                   => None,
         }
     }
 
+    /// Encode this key using the CSI-u (fixterms) disambiguating protocol: `CSI <codepoint> ;
+    /// <mod> u` for character keys, reusing the legacy final byte of named keys but always
+    /// carrying the modifier parameter. Unlike `compatible_code`, Ctrl is never folded into a
+    /// control byte here, so e.g. Ctrl+I is distinct from Tab.
     fn extended_code(&self, modifiers: Modifiers) -> Option<String> {
-        unimplemented!()
+        match *self {
+            Char(true, c)                       => Some(extended_key(modifiers, c as u32)),
+            Cmd(ref s)                           => Some(String::from(&**s)),
+            Up(true)                             => Some(extended_term_key(modifiers, 'A')),
+            Down(true)                           => Some(extended_term_key(modifiers, 'B')),
+            Left(true)                           => Some(extended_term_key(modifiers, 'D')),
+            Right(true)                          => Some(extended_term_key(modifiers, 'C')),
+            ShiftLeft(_)
+                | ShiftRight(_)
+                | CtrlLeft(_)
+                | CtrlRight(_)
+                | AltLeft(_)
+                | AltRight(_)
+                | CapsLock(_)                    => unreachable!(),
+            MetaLeft(true) | MetaRight(true)     => None,
+            PageUp(true)                         => Some(extended_tilde_key(modifiers, "5")),
+            PageDown(true)                       => Some(extended_tilde_key(modifiers, "6")),
+            Home(true)                           => Some(extended_term_key(modifiers, 'H')),
+            End(true)                            => Some(extended_term_key(modifiers, 'F')),
+            Insert(true)                         => Some(extended_tilde_key(modifiers, "2")),
+            Delete(true)                         => Some(extended_tilde_key(modifiers, "3")),
+            NumLock(_)                           => None,
+            ScrollLock(_)                        => None,
+            Function(true, n)                   => extended_function_key(modifiers, n),
+            _                                    => None,
+        }
     }
 
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct Modifiers {
     pub shift: bool,
     pub caps: bool,
     pub ctrl: bool,
     pub alt: bool,
+    pub meta: bool,
 }
 
 impl Modifiers {
@@ -92,7 +119,8 @@ impl Modifiers {
             shift: false,
             caps: false,
             ctrl: false,
-            alt: false
+            alt: false,
+            meta: false,
         }
     }
 
@@ -100,6 +128,15 @@ impl Modifiers {
         (self.shift || self.caps, self.ctrl, self.alt)
     }
 
+    /// The CSI-u modifier parameter: 1 plus a bitmask of the held modifiers (shift = 1, alt = 2,
+    /// ctrl = 4, meta = 8).
+    fn extended_modifier(&self) -> u8 {
+        1   + (self.shift || self.caps) as u8
+            + (self.alt as u8) * 2
+            + (self.ctrl as u8) * 4
+            + (self.meta as u8) * 8
+    }
+
 }
 
 fn char_key(modifiers: Modifiers, c: char) -> Option<String> {
@@ -131,7 +168,43 @@ fn term_key(modifiers: Modifiers, term: char, ansi: bool) -> Option<String> {
     }
 }
 
-fn tilde_key(modifiers: Modifiers, init: char) -> Option<String> {
+fn extended_key(modifiers: Modifiers, codepoint: u32) -> String {
+    match modifiers.extended_modifier() {
+        1 => format!("\x1b[{}u", codepoint),
+        m => format!("\x1b[{};{}u", codepoint, m),
+    }
+}
+
+fn extended_term_key(modifiers: Modifiers, term: char) -> String {
+    format!("\x1b[1;{}{}", modifiers.extended_modifier(), term)
+}
+
+fn extended_tilde_key(modifiers: Modifiers, init: &str) -> String {
+    format!("\x1b[{};{}~", init, modifiers.extended_modifier())
+}
+
+/// Encode F1-F12 under the CSI-u protocol: F1-F4 reuse the SS3 final byte in the `CSI 1 ; <mod>
+/// <byte>` form (always carrying the modifier parameter, unlike `function_key`'s SS3 form),
+/// F5-F12 use the tilde form with the modifier parameter appended.
+fn extended_function_key(modifiers: Modifiers, n: u8) -> Option<String> {
+    match n {
+        1  => Some(extended_term_key(modifiers, 'P')),
+        2  => Some(extended_term_key(modifiers, 'Q')),
+        3  => Some(extended_term_key(modifiers, 'R')),
+        4  => Some(extended_term_key(modifiers, 'S')),
+        5  => Some(extended_tilde_key(modifiers, "15")),
+        6  => Some(extended_tilde_key(modifiers, "17")),
+        7  => Some(extended_tilde_key(modifiers, "18")),
+        8  => Some(extended_tilde_key(modifiers, "19")),
+        9  => Some(extended_tilde_key(modifiers, "20")),
+        10 => Some(extended_tilde_key(modifiers, "21")),
+        11 => Some(extended_tilde_key(modifiers, "23")),
+        12 => Some(extended_tilde_key(modifiers, "24")),
+        _  => None,
+    }
+}
+
+fn tilde_key(modifiers: Modifiers, init: &str) -> Option<String> {
     match modifiers.triplet() {
         (false, false, false)           => Some(format!("\x1b[{}~", init)),
         (true,  false, false)           => Some(format!("\x1b[{};2~", init)),
@@ -142,4 +215,107 @@ fn tilde_key(modifiers: Modifiers, init: char) -> Option<String> {
         (false, true,  true)            => Some(format!("\x1b[{};7~", init)),
         (true,  true,  true)            => Some(format!("\x1b[{};8~", init)),
     }
+}
+
+/// Encode F1-F12. F1-F4 use the SS3 sequences (`ESC O P/Q/R/S`), switching to the CSI form
+/// (`ESC [ 1 ; <mod> P/Q/R/S`) once a modifier is held; F5-F12 use the xterm tilde sequences,
+/// routed through `tilde_key` like PageUp/PageDown/Insert/Delete.
+fn function_key(modifiers: Modifiers, n: u8) -> Option<String> {
+    match n {
+        1  => term_key(modifiers, 'P', false),
+        2  => term_key(modifiers, 'Q', false),
+        3  => term_key(modifiers, 'R', false),
+        4  => term_key(modifiers, 'S', false),
+        5  => tilde_key(modifiers, "15"),
+        6  => tilde_key(modifiers, "17"),
+        7  => tilde_key(modifiers, "18"),
+        8  => tilde_key(modifiers, "19"),
+        9  => tilde_key(modifiers, "20"),
+        10 => tilde_key(modifiers, "21"),
+        11 => tilde_key(modifiers, "23"),
+        12 => tilde_key(modifiers, "24"),
+        _  => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datatypes::TermMode;
+    use super::{Key, Modifiers};
+
+    #[test]
+    fn ctrl_i_is_distinct_from_tab_in_extended_mode() {
+        let mut modifiers = Modifiers::new();
+        modifiers.ctrl = true;
+        let code = Key::Char(true, 'i').as_code(TermMode::EXTENDED_KEYBOARD, modifiers);
+        assert_eq!(code, Some(String::from("\x1b[105;5u")));
+    }
+
+    #[test]
+    fn extended_char_with_no_modifiers_omits_the_modifier_parameter() {
+        let code = Key::Char(true, 'a').as_code(TermMode::EXTENDED_KEYBOARD, Modifiers::new());
+        assert_eq!(code, Some(String::from("\x1b[97u")));
+    }
+
+    #[test]
+    fn extended_named_keys_always_carry_the_modifier_parameter() {
+        let code = Key::Up(true).as_code(TermMode::EXTENDED_KEYBOARD, Modifiers::new());
+        assert_eq!(code, Some(String::from("\x1b[1;1A")));
+    }
+
+    #[test]
+    fn extended_mode_does_not_fold_ctrl_into_a_control_byte() {
+        let mut modifiers = Modifiers::new();
+        modifiers.ctrl = true;
+        let extended = Key::Char(true, 'c').as_code(TermMode::EXTENDED_KEYBOARD, modifiers);
+        let ansi = Key::Char(true, 'c').as_code(TermMode::empty(), modifiers);
+        assert_eq!(extended, Some(String::from("\x1b[99;5u")));
+        // The legacy encoding folds Ctrl+C into the control byte ETX (0x03) instead.
+        assert_eq!(ansi, Some("\x03".to_string()));
+    }
+
+    #[test]
+    fn unmodified_f1_sends_ss3() {
+        let code = Key::Function(true, 1).as_code(TermMode::empty(), Modifiers::new());
+        assert_eq!(code, Some(String::from("\x1bOP")));
+    }
+
+    #[test]
+    fn modified_f1_switches_to_the_csi_form() {
+        let mut modifiers = Modifiers::new();
+        modifiers.shift = true;
+        let code = Key::Function(true, 1).as_code(TermMode::empty(), modifiers);
+        assert_eq!(code, Some(String::from("\x1b[1;2P")));
+    }
+
+    #[test]
+    fn f5_through_f12_use_tilde_codes() {
+        let codes: Vec<_> = (5u8...12).map(|n| {
+            Key::Function(true, n).as_code(TermMode::empty(), Modifiers::new())
+        }).collect();
+        assert_eq!(codes, vec![
+            Some(String::from("\x1b[15~")),
+            Some(String::from("\x1b[17~")),
+            Some(String::from("\x1b[18~")),
+            Some(String::from("\x1b[19~")),
+            Some(String::from("\x1b[20~")),
+            Some(String::from("\x1b[21~")),
+            Some(String::from("\x1b[23~")),
+            Some(String::from("\x1b[24~")),
+        ]);
+    }
+
+    #[test]
+    fn extended_mode_encodes_function_keys_instead_of_dropping_them() {
+        let f1 = Key::Function(true, 1).as_code(TermMode::EXTENDED_KEYBOARD, Modifiers::new());
+        assert_eq!(f1, Some(String::from("\x1b[1;1P")));
+        let f5 = Key::Function(true, 5).as_code(TermMode::EXTENDED_KEYBOARD, Modifiers::new());
+        assert_eq!(f5, Some(String::from("\x1b[15;1~")));
+    }
+
+    #[test]
+    fn num_lock_and_scroll_lock_do_not_panic() {
+        assert_eq!(Key::NumLock(true).as_code(TermMode::empty(), Modifiers::new()), None);
+        assert_eq!(Key::ScrollLock(true).as_code(TermMode::empty(), Modifiers::new()), None);
+    }
 }
\ No newline at end of file
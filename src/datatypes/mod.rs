@@ -5,17 +5,18 @@
 mod key;
 mod movement;
 mod region;
+mod term_mode;
 mod vector;
 
 pub use self::key::{Key, Modifiers};
 pub use self::movement::Movement;
 pub use self::region::Region;
+pub use self::term_mode::TermMode;
 pub use self::vector::Vector;
 
 pub mod args {
-    pub use super::{Area, Coords, Color, InputMode, Movement, Region, Style};
+    pub use super::{Area, Coords, Color, Movement, Region, Style};
     pub use super::Area::*;
-    pub use super::InputMode::*;
     pub use super::Movement::*;
     pub use super::Style::*;
 }
@@ -96,18 +97,25 @@ pub enum Direction {
 pub enum InputEvent {
     /// Data which will be transmitted to the controlling process (usually keyboard input).
     Key(Key),
-    /// A mode shift for how the processor should transmit data.
-    Mode(InputMode),
+    /// A mouse click, drag, or wheel scroll to report to the controlling process.
+    Mouse {
+        coords: Coords,
+        button: MouseButton,
+        press: bool,
+        modifiers: Modifiers,
+    },
+    /// Set or clear a bit in the mode flags the processor uses to decide how to transmit data.
+    Mode(TermMode, bool),
 }
 
-/// The mode the input processor is in.
-#[derive(Copy, Clone, Eq, PartialEq)]
-pub enum InputMode {
-    /// ANSI-compatible mode.
-    Ansi,
-    /// ANSI-compatible mode with application arrow key input.
-    Application,
-    Extended,
+/// A mouse button (or wheel direction) involved in a mouse event.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    WheelUp,
+    WheelDown,
 }
 
 /// Set rich text styles. Booleans represent on or off.
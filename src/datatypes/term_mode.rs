@@ -0,0 +1,34 @@
+bitflags! {
+    /// The independent boolean modes a terminal can be in simultaneously, set and cleared by
+    /// DECSET/DECRST (`CSI ? <n> h/l`) escape codes. Kept as one set of bits, rather than a
+    /// handful of mutually exclusive enum variants, because real terminals combine these
+    /// freely -- e.g. application-cursor and bracketed-paste and SGR mouse reporting can all be
+    /// on at once.
+    pub struct TermMode: u32 {
+        /// Arrow keys send `SS3`-prefixed sequences instead of `CSI`-prefixed ones (DECCKM).
+        const APPLICATION_CURSOR  = 1 << 0;
+        /// The numeric keypad sends application sequences instead of digits (DECKPAM).
+        const APPLICATION_KEYPAD  = 1 << 1;
+        /// Enter sends CRLF instead of CR (LNM).
+        const NEWLINE             = 1 << 2;
+        /// Pasted text is wrapped in `ESC [ 200 ~` / `ESC [ 201 ~` so the controlling process can
+        /// tell typed input from a paste.
+        const BRACKETED_PASTE     = 1 << 3;
+        /// Report mouse button presses and releases (`CSI ? 1000 h/l`).
+        const MOUSE_BUTTON_EVENT  = 1 << 4;
+        /// Report mouse button presses, releases, and motion (`CSI ? 1003 h/l`).
+        const MOUSE_ANY_EVENT     = 1 << 5;
+        /// Encode mouse events with the SGR (1006) protocol instead of legacy X10.
+        const MOUSE_SGR           = 1 << 6;
+        /// The text cursor is visible (DECTCEM).
+        const SHOW_CURSOR         = 1 << 7;
+        /// Cursor coordinates are relative to the scroll region margins (DECOM).
+        const ORIGIN              = 1 << 8;
+        /// Backspace in the first column wraps to the last column of the previous row (reverse
+        /// wraparound).
+        const REVERSE_WRAPAROUND  = 1 << 9;
+        /// Keys are encoded with the CSI-u (fixterms) disambiguating protocol instead of the
+        /// legacy ANSI/application encoding.
+        const EXTENDED_KEYBOARD   = 1 << 10;
+    }
+}
@@ -0,0 +1,205 @@
+//! OSC 52 clipboard integration: `OSC 52 ; <selection> ; <payload> ST` lets the controlling
+//! process read and write the system clipboard (`c`) or primary selection (`p`), with the
+//! payload base64-encoded and `?` used to query the current contents.
+
+/// Which X11-style selection an OSC 52 sequence addresses.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ClipboardSelection {
+    /// The `c` (clipboard) selection.
+    Clipboard,
+    /// The `p` (primary) selection.
+    Primary,
+}
+
+impl ClipboardSelection {
+    fn from_char(c: char) -> Option<ClipboardSelection> {
+        match c {
+            'c' => Some(ClipboardSelection::Clipboard),
+            'p' => Some(ClipboardSelection::Primary),
+            _   => None,
+        }
+    }
+
+    fn as_char(&self) -> char {
+        match *self {
+            ClipboardSelection::Clipboard => 'c',
+            ClipboardSelection::Primary   => 'p',
+        }
+    }
+}
+
+/// A sink for clipboard reads and writes, implemented by the embedder. Defaults to a no-op that
+/// reports an empty clipboard and drops writes.
+pub trait Clipboard {
+    fn get(&self, selection: ClipboardSelection) -> String {
+        let _ = selection;
+        String::new()
+    }
+
+    fn set(&mut self, selection: ClipboardSelection, content: String) {
+        let _ = (selection, content);
+    }
+}
+
+/// The default clipboard sink: reads as empty, writes are dropped.
+pub struct NoopClipboard;
+
+impl Clipboard for NoopClipboard {}
+
+/// Handle an OSC 52 sequence's selection letter and payload against `sink`. A `?` payload is a
+/// query, answered with the full `OSC 52 ; <selection> ; <payload> ST` response to write back
+/// through `Input` if `allow_read` is true (dropped otherwise, so untrusted output can't
+/// silently exfiltrate clipboard contents); any other payload is base64-decoded and written to
+/// `sink` (dropped if `allow_write` is false, so untrusted output can't silently overwrite the
+/// user's clipboard). Returns `None` if there is nothing to write back, or `selection`/`payload`
+/// is malformed.
+pub fn handle(sink: &mut Clipboard,
+              selection: char,
+              payload: &str,
+              allow_read: bool,
+              allow_write: bool) -> Option<String>
+{
+    let selection = match ClipboardSelection::from_char(selection) {
+        Some(selection) => selection,
+        None            => return None,
+    };
+    if payload == "?" {
+        if allow_read {
+            let reply = encode(sink.get(selection).as_bytes());
+            Some(format!("\x1b]52;{};{}\x07", selection.as_char(), reply))
+        } else {
+            None
+        }
+    } else {
+        if allow_write {
+            if let Some(content) = decode(payload).and_then(|bytes| String::from_utf8(bytes).ok()) {
+                sink.set(selection, content);
+            }
+        }
+        None
+    }
+}
+
+const ALPHABET: &'static [u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let b0 = chunk[0];
+        let b1 = *chunk.get(1).unwrap_or(&0);
+        let b2 = *chunk.get(2).unwrap_or(&0);
+        out.push(ALPHABET[(b0 >> 2) as usize] as char);
+        out.push(ALPHABET[(((b0 & 0x03) << 4) | (b1 >> 4)) as usize] as char);
+        out.push(if chunk.len() > 1 {
+            ALPHABET[(((b1 & 0x0f) << 2) | (b2 >> 6)) as usize] as char
+        } else {
+            '='
+        });
+        out.push(if chunk.len() > 2 { ALPHABET[(b2 & 0x3f) as usize] as char } else { '=' });
+    }
+    out
+}
+
+fn decode(input: &str) -> Option<Vec<u8>> {
+    fn value(byte: u8) -> Option<u8> {
+        match byte {
+            b'A'...b'Z' => Some(byte - b'A'),
+            b'a'...b'z' => Some(byte - b'a' + 26),
+            b'0'...b'9' => Some(byte - b'0' + 52),
+            b'+'        => Some(62),
+            b'/'        => Some(63),
+            _           => None,
+        }
+    }
+    let bytes: Vec<u8> = input.bytes().filter(|&b| b != b'=').collect();
+    let mut out = Vec::with_capacity(bytes.len() * 3 / 4);
+    for chunk in bytes.chunks(4) {
+        let vals = chunk.iter().cloned().map(value).collect::<Option<Vec<u8>>>()?;
+        out.push((vals[0] << 2) | (vals.get(1).map_or(0, |v| v >> 4)));
+        if vals.len() > 2 {
+            out.push((vals[1] << 4) | (vals[2] >> 2));
+        }
+        if vals.len() > 3 {
+            out.push((vals[2] << 6) | vals[3]);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode, handle, Clipboard, ClipboardSelection};
+
+    struct TestClipboard {
+        clipboard: String,
+        primary: String,
+    }
+
+    impl Clipboard for TestClipboard {
+        fn get(&self, selection: ClipboardSelection) -> String {
+            match selection {
+                ClipboardSelection::Clipboard => self.clipboard.clone(),
+                ClipboardSelection::Primary   => self.primary.clone(),
+            }
+        }
+
+        fn set(&mut self, selection: ClipboardSelection, content: String) {
+            match selection {
+                ClipboardSelection::Clipboard => self.clipboard = content,
+                ClipboardSelection::Primary   => self.primary = content,
+            }
+        }
+    }
+
+    #[test]
+    fn base64_round_trips_through_encode_and_decode() {
+        for text in &["", "a", "hi", "hello", "hello!", "hello!!"] {
+            let decoded = decode(&encode(text.as_bytes())).unwrap();
+            assert_eq!(decoded, text.as_bytes());
+        }
+    }
+
+    #[test]
+    fn encode_matches_known_vectors() {
+        assert_eq!(encode(b"f"), "Zg==");
+        assert_eq!(encode(b"fo"), "Zm8=");
+        assert_eq!(encode(b"foo"), "Zm9v");
+    }
+
+    #[test]
+    fn query_replies_with_the_sink_contents_base64_encoded() {
+        let mut sink = TestClipboard { clipboard: String::from("foo"), primary: String::new() };
+        let reply = handle(&mut sink, 'c', "?", true, false);
+        assert_eq!(reply, Some(String::from("\x1b]52;c;Zm9v\x07")));
+    }
+
+    #[test]
+    fn query_is_dropped_when_reads_are_not_allowed() {
+        let mut sink = TestClipboard { clipboard: String::from("foo"), primary: String::new() };
+        let reply = handle(&mut sink, 'c', "?", false, false);
+        assert_eq!(reply, None);
+    }
+
+    #[test]
+    fn set_is_dropped_when_writes_are_not_allowed() {
+        let mut sink = TestClipboard { clipboard: String::new(), primary: String::new() };
+        let reply = handle(&mut sink, 'c', &encode(b"foo"), false, false);
+        assert_eq!(reply, None);
+        assert_eq!(sink.clipboard, "");
+    }
+
+    #[test]
+    fn set_stores_the_decoded_payload_when_writes_are_allowed() {
+        let mut sink = TestClipboard { clipboard: String::new(), primary: String::new() };
+        let reply = handle(&mut sink, 'p', &encode(b"foo"), false, true);
+        assert_eq!(reply, None);
+        assert_eq!(sink.primary, "foo");
+    }
+
+    #[test]
+    fn unknown_selection_letter_is_ignored() {
+        let mut sink = TestClipboard { clipboard: String::new(), primary: String::new() };
+        assert_eq!(handle(&mut sink, 'x', "?", true, false), None);
+    }
+}
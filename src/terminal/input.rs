@@ -0,0 +1,55 @@
+use std::io::{self, Write};
+
+use datatypes::{Key, Modifiers, TermMode};
+
+/// Wraps the pty descriptor together with the state needed to turn a `Key` into the bytes it
+/// sends: the current `TermMode` bits (which escape dialect to encode with) and the live
+/// modifier keys.
+pub struct Input {
+    tty: Box<Write>,
+    mode: TermMode,
+    modifiers: Modifiers,
+}
+
+impl Input {
+
+    pub fn new<W: Write + 'static>(tty: W) -> Input {
+        Input {
+            tty: Box::new(tty),
+            mode: TermMode::SHOW_CURSOR,
+            modifiers: Modifiers::new(),
+        }
+    }
+
+    pub fn write(&mut self, key: Key, press: bool) -> io::Result<()> {
+        match key {
+            Key::ShiftLeft(_) | Key::ShiftRight(_)  => { self.modifiers.shift = press; return Ok(()) }
+            Key::CtrlLeft(_) | Key::CtrlRight(_)    => { self.modifiers.ctrl = press; return Ok(()) }
+            Key::AltLeft(_) | Key::AltRight(_)      => { self.modifiers.alt = press; return Ok(()) }
+            Key::MetaLeft(_) | Key::MetaRight(_)    => { self.modifiers.meta = press; return Ok(()) }
+            Key::CapsLock(_)                        => {
+                if press { self.modifiers.caps = !self.modifiers.caps; }
+                return Ok(())
+            }
+            _                                       => (),
+        }
+        if !press {
+            return Ok(());
+        }
+        match key.as_code(self.mode, self.modifiers) {
+            Some(code) => self.tty.write_all(code.as_bytes()),
+            None       => Ok(()),
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: TermMode) {
+        self.mode = mode;
+    }
+
+    /// Write an already-encoded escape sequence straight to the pty, for events (mouse reports,
+    /// clipboard replies) that don't go through `Key::as_code`.
+    pub fn write_str(&mut self, code: &str) -> io::Result<()> {
+        self.tty.write_all(code.as_bytes())
+    }
+
+}
@@ -13,36 +13,52 @@
 //  
 //  You should have received a copy of the GNU Affero General Public License
 //  along with this program.  If not, see <http://www.gnu.org/licenses/>.
+use std::collections::VecDeque;
 use std::io::{self, Write};
 use std::mem;
 use std::ops::{Deref, DerefMut};
 
 mod cell;
 mod char_grid;
+mod clipboard;
 mod cursor;
 mod grid;
 mod input;
+mod mouse;
 mod styles;
 mod tooltip;
+mod vi_mode;
 
-use datatypes::{InputMode, Key};
+use datatypes::{Coords, Key, MouseButton, Modifiers, TermMode};
 
 pub use self::cell::CharCell;
 pub use self::char_grid::CharGrid;
+pub use self::clipboard::{Clipboard, ClipboardSelection, NoopClipboard};
 pub use self::cursor::Cursor;
 pub use self::grid::Grid;
+pub use self::mouse::{MouseEncoding, MouseTracking};
 pub use self::styles::Styles;
 pub use self::tooltip::Tooltip;
+pub use self::vi_mode::{CharSource, Selection, ViMode, ViMotion};
 
 use self::input::Input;
 
+/// The maximum depth of the window-title stack, after which the oldest saved title is
+/// discarded to make room (`XTPUSHTITLE` has no bound of its own).
+const MAX_TITLE_STACK: usize = 4096;
+
 pub struct Terminal {
     pub width: u32,
     pub height: u32,
     title: String,
+    title_stack: VecDeque<String>,
     active: CharGrid,
     inactive: Vec<CharGrid>,
     tty: Input,
+    mode: TermMode,
+    clipboard: Box<Clipboard>,
+    allow_clipboard_read: bool,
+    allow_clipboard_write: bool,
 }
 
 impl Terminal {
@@ -54,9 +70,14 @@ impl Terminal {
             width: width,
             height: height,
             title: String::new(),
+            title_stack: VecDeque::new(),
             active: grid,
             inactive: Vec::new(),
             tty: tty,
+            mode: TermMode::SHOW_CURSOR,
+            clipboard: Box::new(NoopClipboard),
+            allow_clipboard_read: false,
+            allow_clipboard_write: false,
         }
     }
 
@@ -91,8 +112,119 @@ impl Terminal {
         self.title = title;
     }
 
-    pub fn set_input_mode(&mut self, mode: InputMode) {
-        self.tty.set_mode(mode);
+    /// Save the current title on the title stack, for `XTPUSHTITLE` (`CSI 22 ; <0|1|2> t`). The
+    /// oldest entry is discarded once the stack reaches `MAX_TITLE_STACK`.
+    pub fn push_title(&mut self) {
+        if self.title_stack.len() == MAX_TITLE_STACK {
+            self.title_stack.pop_front();
+        }
+        self.title_stack.push_back(self.title.clone());
+    }
+
+    /// Restore the most recently saved title, for `XTPOPTITLE` (`CSI 23 ; <0|1|2> t`). A no-op
+    /// if the title stack is empty.
+    pub fn pop_title(&mut self) {
+        if let Some(title) = self.title_stack.pop_back() {
+            self.title = title;
+        }
+    }
+
+    /// Set or clear bits in the terminal's mode flags, e.g. in response to a DECSET/DECRST
+    /// (`CSI ? <n> h/l`) escape code, and sync the new state to the input processor.
+    pub fn set_mode(&mut self, bits: TermMode, set: bool) {
+        if set {
+            self.mode.insert(bits);
+        } else {
+            self.mode.remove(bits);
+        }
+        self.tty.set_mode(self.mode);
+    }
+
+    /// Wrap `text` in `ESC [ 200 ~` / `ESC [ 201 ~` if bracketed-paste mode is on, so the
+    /// controlling process can tell a paste from typed input; otherwise return it unchanged.
+    pub fn bracket_paste(&self, text: &str) -> String {
+        if self.mode.contains(TermMode::BRACKETED_PASTE) {
+            format!("\x1b[200~{}\x1b[201~", text)
+        } else {
+            String::from(text)
+        }
+    }
+
+    fn mouse_tracking(&self) -> MouseTracking {
+        if self.mode.contains(TermMode::MOUSE_ANY_EVENT) {
+            MouseTracking::AnyEvent
+        } else if self.mode.contains(TermMode::MOUSE_BUTTON_EVENT) {
+            MouseTracking::ButtonEvent
+        } else {
+            MouseTracking::Off
+        }
+    }
+
+    fn mouse_encoding(&self) -> MouseEncoding {
+        if self.mode.contains(TermMode::MOUSE_SGR) { MouseEncoding::Sgr } else { MouseEncoding::X10 }
+    }
+
+    /// Report a mouse button press or release, or a wheel scroll, to the controlling process,
+    /// encoded according to the current tracking mode and wire format. A no-op if mouse tracking
+    /// is off. Drags are not reported as motion: the motion bit is never set here.
+    pub fn send_mouse(&mut self,
+                       coords: Coords,
+                       button: MouseButton,
+                       press: bool,
+                       modifiers: Modifiers) -> io::Result<()> {
+        match mouse::encode(coords, button, press, modifiers, self.mouse_tracking(),
+                             self.mouse_encoding()) {
+            Some(code) => self.tty.write_str(&code),
+            None       => Ok(()),
+        }
+    }
+
+    /// Report the pointer moving with no button held to the controlling process. A no-op unless
+    /// tracking is set to report any event (`CSI ? 1003 h`), since button-event tracking has
+    /// nothing to say about motion on its own.
+    pub fn send_mouse_motion(&mut self, coords: Coords, modifiers: Modifiers) -> io::Result<()> {
+        match mouse::encode_motion(coords, modifiers, self.mouse_tracking(), self.mouse_encoding()) {
+            Some(code) => self.tty.write_str(&code),
+            None       => Ok(()),
+        }
+    }
+
+    /// Install the clipboard sink the embedder uses to back `c` (clipboard) and `p` (primary)
+    /// OSC 52 selections. Defaults to a no-op sink.
+    pub fn set_clipboard(&mut self, clipboard: Box<Clipboard>) {
+        self.clipboard = clipboard;
+    }
+
+    /// Gate whether `handle_osc52` is allowed to read the clipboard sink back to the
+    /// controlling process. Off by default, since a `?` query is how OSC 52 is used to
+    /// exfiltrate clipboard contents to an untrusted remote session.
+    pub fn set_clipboard_read_enabled(&mut self, allow: bool) {
+        self.allow_clipboard_read = allow;
+    }
+
+    /// Gate whether `handle_osc52` is allowed to write to the clipboard sink. Off by default.
+    pub fn set_clipboard_write_enabled(&mut self, allow: bool) {
+        self.allow_clipboard_write = allow;
+    }
+
+    /// Handle an `OSC 52 ; <selection> ; <payload> ST` sequence: a `?` payload queries the
+    /// clipboard sink and writes the base64-encoded reply back through `Input`, if reads are
+    /// enabled; any other payload is base64-decoded and stored in the sink, if writes are
+    /// enabled.
+    pub fn handle_osc52(&mut self, selection: char, payload: &str) -> io::Result<()> {
+        match clipboard::handle(&mut *self.clipboard, selection, payload,
+                                 self.allow_clipboard_read, self.allow_clipboard_write) {
+            Some(reply) => self.tty.write_str(&reply),
+            None        => Ok(()),
+        }
+    }
+
+    /// Copy a vi-mode selection's text into the clipboard sink. Pairs `ViMode`'s selection with
+    /// the clipboard abstraction so a yank writes to the same place OSC 52 reads from.
+    pub fn yank<G: CharSource>(&mut self, vi: &ViMode, grid: &G, selection: ClipboardSelection) {
+        if let Some(text) = vi.yank_text(grid) {
+            self.clipboard.set(selection, text);
+        }
     }
 
     pub fn bell(&mut self) {
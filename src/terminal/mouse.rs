@@ -0,0 +1,159 @@
+use datatypes::{Coords, Modifiers, MouseButton};
+
+/// Which mouse events are reported to the controlling process, set via the DECSET/DECRST
+/// sequences `CSI ? 1000 h/l` (button-event) and `CSI ? 1003 h/l` (any-event).
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MouseTracking {
+    /// Mouse events are not reported.
+    Off,
+    /// Report button presses and releases.
+    ButtonEvent,
+    /// Report button presses and releases, and pointer motion with no button held. Motion with a
+    /// button held (a drag) is not encoded as motion.
+    AnyEvent,
+}
+
+/// Which wire format mouse events are encoded in, set via `CSI ? 1006 h/l`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MouseEncoding {
+    /// The legacy X10 protocol: `ESC [ M` followed by three bytes packing the button and
+    /// coordinates, each offset by 32. Coordinates above 223 cannot be represented.
+    X10,
+    /// The SGR (1006) protocol: `ESC [ < Cb ; x ; y M` for a press, `... m` for a release. Has
+    /// no coordinate limit.
+    Sgr,
+}
+
+/// Encode a mouse event for transmission to the controlling process, or `None` if `tracking`
+/// says this event should not be reported at all.
+pub fn encode(coords: Coords,
+              button: MouseButton,
+              press: bool,
+              modifiers: Modifiers,
+              tracking: MouseTracking,
+              encoding: MouseEncoding) -> Option<String> {
+    if tracking == MouseTracking::Off {
+        return None;
+    }
+    let cb = button_code(Some(button), modifiers);
+    match encoding {
+        MouseEncoding::X10 => Some(x10_code(cb, coords, press)),
+        MouseEncoding::Sgr => Some(sgr_code(cb, coords, press)),
+    }
+}
+
+/// Encode a passive motion event (the pointer moved with no button held), or `None` if
+/// `tracking` is anything but `AnyEvent` — button-event tracking only reports presses and
+/// releases, so motion with no button down has nothing to report under it.
+pub fn encode_motion(coords: Coords,
+                      modifiers: Modifiers,
+                      tracking: MouseTracking,
+                      encoding: MouseEncoding) -> Option<String> {
+    if tracking != MouseTracking::AnyEvent {
+        return None;
+    }
+    // Bit 5 (32) marks a motion event; the no-button base code is 3, same as an X10 release.
+    let cb = button_code(None, modifiers) | 0x20;
+    match encoding {
+        MouseEncoding::X10 => Some(x10_code(cb, coords, true)),
+        MouseEncoding::Sgr => Some(sgr_code(cb, coords, true)),
+    }
+}
+
+/// Pack the button index and modifier bits into the `Cb` byte shared by both encodings: wheel
+/// buttons set bit 6, shift/alt/ctrl set bits 2, 3, and 4 respectively, and `None` (no button,
+/// for motion events) uses the same base code as an X10 release.
+fn button_code(button: Option<MouseButton>, modifiers: Modifiers) -> u8 {
+    let base = match button {
+        Some(MouseButton::Left)       => 0,
+        Some(MouseButton::Middle)     => 1,
+        Some(MouseButton::Right)      => 2,
+        Some(MouseButton::WheelUp)    => 64,
+        Some(MouseButton::WheelDown)  => 65,
+        None                         => 3,
+    };
+    base | (modifiers.shift as u8) << 2
+         | (modifiers.alt as u8) << 3
+         | (modifiers.ctrl as u8) << 4
+}
+
+fn x10_code(cb: u8, coords: Coords, press: bool) -> String {
+    // X10 has no way to report which button was released, so releases always use code 3.
+    let cb = if press { cb } else { 3 };
+    format!("\x1b[M{}{}{}",
+            (32 + cb) as char,
+            (32 + 1 + coords.x.min(222) as u8) as char,
+            (32 + 1 + coords.y.min(222) as u8) as char)
+}
+
+fn sgr_code(cb: u8, coords: Coords, press: bool) -> String {
+    format!("\x1b[<{};{};{}{}", cb, coords.x + 1, coords.y + 1, if press { 'M' } else { 'm' })
+}
+
+#[cfg(test)]
+mod tests {
+    use datatypes::{Coords, Modifiers, MouseButton};
+    use super::{encode, encode_motion, MouseEncoding, MouseTracking};
+
+    #[test]
+    fn off_reports_nothing() {
+        let coords = Coords { x: 1, y: 2 };
+        assert_eq!(encode(coords, MouseButton::Left, true, Modifiers::new(),
+                           MouseTracking::Off, MouseEncoding::X10), None);
+    }
+
+    #[test]
+    fn x10_press_offsets_button_and_coords_by_32() {
+        let coords = Coords { x: 3, y: 4 };
+        let code = encode(coords, MouseButton::Left, true, Modifiers::new(),
+                           MouseTracking::ButtonEvent, MouseEncoding::X10).unwrap();
+        assert_eq!(code, "\x1b[M $%");
+    }
+
+    #[test]
+    fn x10_release_always_uses_button_code_3() {
+        let coords = Coords { x: 0, y: 0 };
+        let code = encode(coords, MouseButton::Right, false, Modifiers::new(),
+                           MouseTracking::ButtonEvent, MouseEncoding::X10).unwrap();
+        assert_eq!(code, "\x1b[M#!!");
+    }
+
+    #[test]
+    fn sgr_press_and_release_share_coordinates_but_differ_in_final_byte() {
+        let coords = Coords { x: 9, y: 19 };
+        let press = encode(coords, MouseButton::Middle, true, Modifiers::new(),
+                            MouseTracking::ButtonEvent, MouseEncoding::Sgr).unwrap();
+        let release = encode(coords, MouseButton::Middle, false, Modifiers::new(),
+                              MouseTracking::ButtonEvent, MouseEncoding::Sgr).unwrap();
+        assert_eq!(press, "\x1b[<1;10;20M");
+        assert_eq!(release, "\x1b[<1;10;20m");
+    }
+
+    #[test]
+    fn sgr_sets_modifier_bits() {
+        let mut modifiers = Modifiers::new();
+        modifiers.shift = true;
+        modifiers.ctrl = true;
+        let coords = Coords { x: 0, y: 0 };
+        let code = encode(coords, MouseButton::Left, true, modifiers,
+                           MouseTracking::ButtonEvent, MouseEncoding::Sgr).unwrap();
+        // shift (bit 2) | ctrl (bit 4) | left (0) = 0b10100 = 20
+        assert_eq!(code, "\x1b[<20;1;1M");
+    }
+
+    #[test]
+    fn motion_is_not_reported_under_button_event_tracking() {
+        let coords = Coords { x: 1, y: 2 };
+        assert_eq!(encode_motion(coords, Modifiers::new(),
+                                  MouseTracking::ButtonEvent, MouseEncoding::Sgr), None);
+    }
+
+    #[test]
+    fn any_event_tracking_reports_motion_with_the_motion_bit_set() {
+        let coords = Coords { x: 0, y: 0 };
+        // no-button base (3) | motion bit (32) = 35
+        let code = encode_motion(coords, Modifiers::new(),
+                                  MouseTracking::AnyEvent, MouseEncoding::Sgr).unwrap();
+        assert_eq!(code, "\x1b[<35;1;1M");
+    }
+}
@@ -0,0 +1,277 @@
+//! A modal, keyboard-only cursor for navigating and selecting text in a `CharGrid`, kept
+//! separate from the shell's own cursor. This is the foundation for mouse-free copy: motions
+//! move a `ViMode`'s own position around the grid, and a `Selection` anchored at one point and
+//! extended to another becomes the `Region` handed to a copy action.
+
+use datatypes::{Coords, Direction, Region};
+
+/// Anything a `ViMode` can move its cursor across. `CharGrid` does not implement this; the
+/// caller supplies an implementation that reads the grid's cells.
+pub trait CharSource {
+    fn width(&self) -> u32;
+    fn height(&self) -> u32;
+    /// The character in the cell at `coords`, or a space if the cell is empty.
+    fn char_at(&self, coords: Coords) -> char;
+    /// Whether the row at `coords.y` wraps onto the next row (i.e. is not a logical line break).
+    fn row_wraps(&self, coords: Coords) -> bool;
+}
+
+/// A motion for a `ViMode` cursor. Mirrors the shape of `datatypes::Movement`, but adds the
+/// screen- and document-relative jumps vi-mode needs and none of the scrollback-altering ones
+/// `Movement` covers.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ViMotion {
+    /// Move one cell in a direction.
+    Cell(Direction),
+    /// Move to the start of the next (or previous) word.
+    Word(Direction),
+    /// Move to the start or end of the current logical line.
+    Line(Direction),
+    /// Move to the top row of the visible screen.
+    ScreenTop,
+    /// Move to the bottom row of the visible screen.
+    ScreenBottom,
+    /// Move to the first cell of the document.
+    DocumentStart,
+    /// Move to the last cell of the document.
+    DocumentEnd,
+}
+
+/// Characters which end a word in addition to whitespace.
+const SEPARATORS: &'static [char] = &[',', '.', ';', ':', '(', ')', '[', ']', '{', '}', '\'', '"'];
+
+/// A selection anchored at one point and extended to another.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Selection {
+    pub anchor: Coords,
+    pub point: Coords,
+}
+
+impl Selection {
+    pub fn new(at: Coords) -> Selection {
+        Selection { anchor: at, point: at }
+    }
+
+    /// The selection as a normalized `Region`, regardless of which end the anchor sits at.
+    pub fn region(&self) -> Region {
+        let (start, end) = if (self.anchor.y, self.anchor.x) <= (self.point.y, self.point.x) {
+            (self.anchor, self.point)
+        } else {
+            (self.point, self.anchor)
+        };
+        Region::new(start, end)
+    }
+}
+
+/// A modal, keyboard-driven cursor for navigating and selecting text, decoupled from the shell
+/// cursor tracked by `Terminal`/`CharGrid`.
+pub struct ViMode {
+    pub cursor: Coords,
+    pub selection: Option<Selection>,
+}
+
+impl ViMode {
+    pub fn new(at: Coords) -> ViMode {
+        ViMode { cursor: at, selection: None }
+    }
+
+    /// Begin a visual selection anchored at the current cursor position.
+    pub fn start_selection(&mut self) {
+        self.selection = Some(Selection::new(self.cursor));
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selection = None;
+    }
+
+    /// Move the cursor, extending the active selection (if any) to follow it.
+    pub fn mov<G: CharSource>(&mut self, grid: &G, motion: ViMotion) {
+        self.cursor = resolve(grid, self.cursor, motion);
+        if let Some(ref mut selection) = self.selection {
+            selection.point = self.cursor;
+        }
+    }
+
+    /// Select the word under the cursor: expand left and right over a run of non-whitespace,
+    /// non-separator characters.
+    pub fn select_word<G: CharSource>(&mut self, grid: &G) {
+        let (start, end) = word_bounds(grid, self.cursor);
+        self.selection = Some(Selection { anchor: start, point: end });
+        self.cursor = end;
+    }
+
+    /// Select the logical line (spanning wrapped rows) the cursor is on.
+    pub fn select_line<G: CharSource>(&mut self, grid: &G) {
+        let (start, end) = line_bounds(grid, self.cursor);
+        self.selection = Some(Selection { anchor: start, point: end });
+        self.cursor = end;
+    }
+
+    /// The text of the active selection, read cell by cell from `grid` with rows joined by
+    /// `\n`, or `None` if there is no selection. This is the input to a yank/copy action.
+    pub fn yank_text<G: CharSource>(&self, grid: &G) -> Option<String> {
+        let selection = match self.selection {
+            Some(ref selection) => selection,
+            None                => return None,
+        };
+        let (start, end) = if (selection.anchor.y, selection.anchor.x)
+            <= (selection.point.y, selection.point.x)
+        {
+            (selection.anchor, selection.point)
+        } else {
+            (selection.point, selection.anchor)
+        };
+        let mut text = String::new();
+        for y in start.y...end.y {
+            let row_start = if y == start.y { start.x } else { 0 };
+            let row_end = if y == end.y { end.x } else { grid.width() - 1 };
+            for x in row_start...row_end {
+                text.push(grid.char_at(Coords { x: x, y: y }));
+            }
+            if y != end.y {
+                text.push('\n');
+            }
+        }
+        Some(text)
+    }
+}
+
+fn resolve<G: CharSource>(grid: &G, from: Coords, motion: ViMotion) -> Coords {
+    match motion {
+        ViMotion::Cell(direction)  => step(grid, from, direction),
+        ViMotion::Word(direction)  => word_step(grid, from, direction),
+        ViMotion::Line(Direction::Left)
+            | ViMotion::Line(Direction::Up)    => line_bounds(grid, from).0,
+        ViMotion::Line(Direction::Right)
+            | ViMotion::Line(Direction::Down)  => line_bounds(grid, from).1,
+        ViMotion::ScreenTop                     => Coords { x: from.x, y: 0 },
+        ViMotion::ScreenBottom                  => Coords { x: from.x, y: grid.height() - 1 },
+        ViMotion::DocumentStart                 => Coords { x: 0, y: 0 },
+        ViMotion::DocumentEnd                   => Coords { x: grid.width() - 1, y: grid.height() - 1 },
+    }
+}
+
+fn step<G: CharSource>(grid: &G, from: Coords, direction: Direction) -> Coords {
+    match direction {
+        Direction::Up    => Coords { x: from.x, y: from.y.saturating_sub(1) },
+        Direction::Down  => Coords { x: from.x, y: (from.y + 1).min(grid.height() - 1) },
+        Direction::Left  => Coords { x: from.x.saturating_sub(1), y: from.y },
+        Direction::Right => Coords { x: (from.x + 1).min(grid.width() - 1), y: from.y },
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !SEPARATORS.contains(&c)
+}
+
+/// Expand left and right from `at` over a run of word characters, stopping at whitespace or a
+/// separator. If `at` itself isn't a word character, the selection doesn't expand at all.
+fn word_bounds<G: CharSource>(grid: &G, at: Coords) -> (Coords, Coords) {
+    if !is_word_char(grid.char_at(at)) {
+        return (at, at);
+    }
+    let mut start = at;
+    while start.x > 0 && is_word_char(grid.char_at(Coords { x: start.x - 1, y: start.y })) {
+        start.x -= 1;
+    }
+    let mut end = at;
+    while end.x + 1 < grid.width() && is_word_char(grid.char_at(Coords { x: end.x + 1, y: end.y })) {
+        end.x += 1;
+    }
+    (start, end)
+}
+
+fn word_step<G: CharSource>(grid: &G, from: Coords, direction: Direction) -> Coords {
+    let (start, end) = word_bounds(grid, from);
+    match direction {
+        Direction::Left | Direction::Up      => {
+            let mut at = step(grid, start, Direction::Left);
+            while at.x > 0 && !is_word_char(grid.char_at(at)) {
+                at.x -= 1;
+            }
+            word_bounds(grid, at).0
+        }
+        Direction::Right | Direction::Down    => {
+            let mut at = step(grid, end, Direction::Right);
+            while at.x + 1 < grid.width() && !is_word_char(grid.char_at(at)) {
+                at.x += 1;
+            }
+            at
+        }
+    }
+}
+
+/// Expand to the start and end of the logical line (the run of rows joined by wrapping) that
+/// `at` sits on.
+fn line_bounds<G: CharSource>(grid: &G, at: Coords) -> (Coords, Coords) {
+    let mut top = at.y;
+    while top > 0 && grid.row_wraps(Coords { x: 0, y: top - 1 }) {
+        top -= 1;
+    }
+    let mut bottom = at.y;
+    while grid.row_wraps(Coords { x: 0, y: bottom }) && bottom + 1 < grid.height() {
+        bottom += 1;
+    }
+    (Coords { x: 0, y: top }, Coords { x: grid.width() - 1, y: bottom })
+}
+
+#[cfg(test)]
+mod tests {
+    use datatypes::Coords;
+    use super::{word_bounds, line_bounds, ViMode, CharSource};
+
+    /// A fixed grid of rows of equal width, with a fixed set of row indices that wrap onto the
+    /// next row, for exercising motions without a real `CharGrid`.
+    struct TestGrid {
+        rows: Vec<&'static str>,
+        wraps: Vec<u32>,
+    }
+
+    impl CharSource for TestGrid {
+        fn width(&self) -> u32 {
+            self.rows[0].len() as u32
+        }
+
+        fn height(&self) -> u32 {
+            self.rows.len() as u32
+        }
+
+        fn char_at(&self, coords: Coords) -> char {
+            self.rows[coords.y as usize].chars().nth(coords.x as usize).unwrap_or(' ')
+        }
+
+        fn row_wraps(&self, coords: Coords) -> bool {
+            self.wraps.contains(&coords.y)
+        }
+    }
+
+    #[test]
+    fn word_bounds_spans_only_the_word_under_the_cursor() {
+        let grid = TestGrid { rows: vec!["foo bar baz"], wraps: vec![] };
+        let (start, end) = word_bounds(&grid, Coords { x: 5, y: 0 });
+        assert_eq!((start.x, end.x), (4, 6));
+    }
+
+    #[test]
+    fn word_bounds_on_whitespace_does_not_merge_the_neighboring_words() {
+        let grid = TestGrid { rows: vec!["foo bar"], wraps: vec![] };
+        let (start, end) = word_bounds(&grid, Coords { x: 3, y: 0 });
+        assert_eq!((start, end), (Coords { x: 3, y: 0 }, Coords { x: 3, y: 0 }));
+    }
+
+    #[test]
+    fn line_bounds_spans_wrapped_rows() {
+        let grid = TestGrid { rows: vec!["aaaa", "bbbb", "cccc"], wraps: vec![0, 1] };
+        let (start, end) = line_bounds(&grid, Coords { x: 1, y: 1 });
+        assert_eq!((start.y, end.y), (0, 2));
+    }
+
+    #[test]
+    fn yank_text_joins_selected_rows_with_newlines() {
+        let grid = TestGrid { rows: vec!["hello", "world"], wraps: vec![] };
+        let mut vi = ViMode::new(Coords { x: 0, y: 0 });
+        vi.start_selection();
+        vi.selection.as_mut().unwrap().point = Coords { x: 4, y: 1 };
+        assert_eq!(vi.yank_text(&grid), Some(String::from("hello\nworld")));
+    }
+}